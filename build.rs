@@ -0,0 +1,194 @@
+//! Generates the `Op` enum, `Op::mnem`, and the `decode` opcode dispatch
+//! from the declarative table in `opcodes.in`, so adding a DWARF5 opcode is
+//! a one-line table edit instead of three hand-synced lists.
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+struct Operand {
+    /// Rust type the field is stored as (after any `:Type` override).
+    field_ty: String,
+    /// Expression (in scope of `op: u8` and `r: &mut Reader`) that produces
+    /// the field's value, already cast to `field_ty`.
+    read_expr: String,
+}
+
+struct Opcode {
+    spec: String, // e.g. "0x03" or "0x30..=0x4f"
+    mnem: String,
+    variant: String,
+    operands: Vec<Operand>,
+    is_raw_op: bool,
+}
+
+fn parse_operand(tok: &str, range_start: Option<&str>) -> Operand {
+    let (kind, override_ty) = match tok.split_once(':') {
+        Some((k, t)) => (k, Some(t)),
+        None => (tok, None),
+    };
+
+    let (natural_ty, expr) = match kind {
+        "U8" => ("u8", "r.read_u8()?".to_string()),
+        "I8" => ("i8", "r.read_i8()?".to_string()),
+        "U16" => ("u16", "r.read_u16()?".to_string()),
+        "I16" => ("i16", "r.read_i16()?".to_string()),
+        "U32" => ("u32", "r.read_u32()?".to_string()),
+        "I32" => ("i32", "r.read_i32()?".to_string()),
+        "U64" => ("u64", "r.read_u64()?".to_string()),
+        "I64" => ("i64", "r.read_i64()?".to_string()),
+        "ULEB" => ("u64", "r.read_uleb128()?".to_string()),
+        "SLEB" => ("i64", "r.read_sleb128()?".to_string()),
+        "BASE" => (
+            "u8",
+            format!(
+                "op - {}",
+                range_start.expect("BASE operand requires a range opcode spec")
+            ),
+        ),
+        "RAW_OP" => ("u8", "op".to_string()),
+        "BLOB_ULEB" => (
+            "Vec<u8>",
+            "{ let len = r.read_uleb128()? as usize; r.read_bytes(len)?.to_vec() }".to_string(),
+        ),
+        "BLOB_U8" => (
+            "Vec<u8>",
+            "{ let len = r.read_u8()? as usize; r.read_bytes(len)?.to_vec() }".to_string(),
+        ),
+        other => panic!("unknown operand kind in opcodes.in: {}", other),
+    };
+
+    match override_ty {
+        Some(ty) => Operand {
+            field_ty: ty.to_string(),
+            read_expr: format!("{} as {}", expr, ty),
+        },
+        None => Operand {
+            field_ty: natural_ty.to_string(),
+            read_expr: expr,
+        },
+    }
+}
+
+fn parse_opcodes(src: &str) -> Vec<Opcode> {
+    let mut opcodes = Vec::new();
+
+    for line in src.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut fields = line.split_whitespace();
+        let spec = fields.next().expect("missing opcode spec").to_string();
+        let mnem = fields.next().expect("missing mnemonic").to_string();
+        let variant = fields.next().expect("missing variant name").to_string();
+
+        let range_start = spec.split_once("..=").map(|(start, _)| start);
+        let operand_toks: Vec<&str> = fields.collect();
+        let is_raw_op = operand_toks.contains(&"RAW_OP");
+        let operands = operand_toks
+            .into_iter()
+            .map(|t| parse_operand(t, range_start))
+            .collect();
+
+        opcodes.push(Opcode {
+            spec,
+            mnem,
+            variant,
+            operands,
+            is_raw_op,
+        });
+    }
+
+    opcodes
+}
+
+fn generate(opcodes: &[Opcode]) -> String {
+    let mut out = String::new();
+
+    // Several non-contiguous vendor ranges share a single catch-all variant
+    // (e.g. `GnuUnknown`), so only emit the enum/mnem definition once per
+    // variant name, keeping every row's own line in the `decode_op` dispatch
+    // below.
+    let mut seen_variants = std::collections::HashSet::new();
+
+    out.push_str("#[derive(Clone, Debug, Hash, Eq, PartialEq)]\npub enum Op {\n");
+    for op in opcodes {
+        if !seen_variants.insert(op.variant.as_str()) {
+            continue;
+        }
+
+        if op.operands.is_empty() {
+            writeln!(out, "    {},", op.variant).unwrap();
+        } else {
+            let tys: Vec<&str> = op.operands.iter().map(|o| o.field_ty.as_str()).collect();
+            writeln!(out, "    {}({}),", op.variant, tys.join(", ")).unwrap();
+        }
+    }
+    out.push_str("}\n\n");
+
+    seen_variants.clear();
+    out.push_str("impl Op {\n    pub fn mnem(&self) -> &str {\n        match self {\n");
+    for op in opcodes {
+        if !seen_variants.insert(op.variant.as_str()) {
+            continue;
+        }
+
+        if op.operands.is_empty() {
+            writeln!(out, "            Self::{} => \"{}\",", op.variant, op.mnem).unwrap();
+        } else {
+            let wildcards = vec!["_"; op.operands.len()].join(", ");
+            writeln!(
+                out,
+                "            Self::{}({}) => \"{}\",",
+                op.variant, wildcards, op.mnem
+            )
+            .unwrap();
+        }
+    }
+    out.push_str("        }\n    }\n}\n\n");
+
+    out.push_str(
+        "pub(crate) fn decode_op(op: u8, r: &mut Reader) -> Result<Op, DwarfDisError> {\n    Ok(match op {\n",
+    );
+    for op in opcodes {
+        let ctor = if op.operands.is_empty() {
+            format!("Op::{}", op.variant)
+        } else {
+            let args: Vec<&str> = op.operands.iter().map(|o| o.read_expr.as_str()).collect();
+            format!("Op::{}({})", op.variant, args.join(", "))
+        };
+
+        if op.is_raw_op {
+            writeln!(
+                out,
+                "        {} => {{ debug!(\"unrecognized vendor opcode: {{:#x}}\", op); {} }}",
+                op.spec, ctor
+            )
+            .unwrap();
+        } else {
+            writeln!(out, "        {} => {},", op.spec, ctor).unwrap();
+        }
+    }
+    out.push_str(
+        "        _ => {\n            error!(\"unimplemented opcode: 0x{:02x}\", op);\n            return Err(DwarfDisError::Decode(op));\n        }\n",
+    );
+    out.push_str("    })\n}\n");
+
+    out
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=opcodes.in");
+
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let src = fs::read_to_string(Path::new(&manifest_dir).join("opcodes.in"))
+        .expect("could not read opcodes.in");
+
+    let opcodes = parse_opcodes(&src);
+    let generated = generate(&opcodes);
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    fs::write(Path::new(&out_dir).join("ops.rs"), generated).expect("could not write ops.rs");
+}
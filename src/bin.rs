@@ -2,7 +2,7 @@ use std::env;
 use std::fs;
 use std::process;
 
-use dwarf_dis::decode;
+use dwarf_dis::Instructions;
 
 use flexi_logger::Logger;
 
@@ -18,16 +18,9 @@ fn main() {
 
     let bytecode = fs::read(&args[1]).expect("Could not read bytecode");
 
-    let mut pc = 0;
-    loop {
-        if pc >= bytecode.len() {
-            break;
-        }
-
-        let (sz, op) = decode(&bytecode[pc..]).unwrap();
+    for insn in Instructions::new(&bytecode) {
+        let (pc, _sz, op) = insn.unwrap();
 
         println!("{:04x}: {:x?}", pc, op);
-
-        pc += sz;
     }
 }
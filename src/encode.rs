@@ -0,0 +1,408 @@
+use nano_leb128::{SLEB128, ULEB128};
+
+use crate::{DwarfDisError, Op};
+
+/// Largest base value `Lit`/`Reg`/`BReg` can encode: each owns a 32-opcode
+/// range (e.g. `Lit` is 0x30..=0x4f), so the base is a 5-bit field.
+const MAX_BASE: u8 = 0x1f;
+
+fn check_base(mnem: &'static str, value: u8) -> Result<(), DwarfDisError> {
+    if value > MAX_BASE {
+        return Err(DwarfDisError::BaseOverflow { mnem, value });
+    }
+
+    Ok(())
+}
+
+fn push_uleb128(buf: &mut Vec<u8>, val: u64) {
+    let mut tmp = [0; 10];
+    let n = ULEB128::from(val).write_into(&mut tmp).unwrap();
+    buf.extend_from_slice(&tmp[..n]);
+}
+
+fn push_sleb128(buf: &mut Vec<u8>, val: i64) {
+    let mut tmp = [0; 10];
+    let n = SLEB128::from(val).write_into(&mut tmp).unwrap();
+    buf.extend_from_slice(&tmp[..n]);
+}
+
+/// Re-emits an `Op` as DWARF bytecode; the exact inverse of `decode`.
+///
+/// Errors if `op` carries a `Lit`/`Reg`/`BReg` base value too large for that
+/// op's opcode range -- `decode` can never produce such an `Op`, but callers
+/// can construct one by hand.
+pub fn encode(op: &Op) -> Result<Vec<u8>, DwarfDisError> {
+    let mut buf = Vec::new();
+
+    match op {
+        Op::Addr(v) => {
+            buf.push(0x03);
+            buf.extend_from_slice(&v.to_le_bytes());
+        }
+        Op::Deref => buf.push(0x06),
+        Op::Const1u(v) => {
+            buf.push(0x08);
+            buf.push(*v);
+        }
+        Op::Const1s(v) => {
+            buf.push(0x09);
+            buf.push(*v as u8);
+        }
+        Op::Const2u(v) => {
+            buf.push(0x0a);
+            buf.extend_from_slice(&v.to_le_bytes());
+        }
+        Op::Const2s(v) => {
+            buf.push(0x0b);
+            buf.extend_from_slice(&v.to_le_bytes());
+        }
+        Op::Const4u(v) => {
+            buf.push(0x0c);
+            buf.extend_from_slice(&v.to_le_bytes());
+        }
+        Op::Const4s(v) => {
+            buf.push(0x0d);
+            buf.extend_from_slice(&v.to_le_bytes());
+        }
+        Op::Const8u(v) => {
+            buf.push(0x0e);
+            buf.extend_from_slice(&v.to_le_bytes());
+        }
+        Op::Const8s(v) => {
+            buf.push(0x0f);
+            buf.extend_from_slice(&v.to_le_bytes());
+        }
+        Op::Constu(v) => {
+            buf.push(0x10);
+            push_uleb128(&mut buf, *v);
+        }
+        Op::Consts(v) => {
+            buf.push(0x11);
+            push_sleb128(&mut buf, *v);
+        }
+        Op::Dup => buf.push(0x12),
+        Op::Drop => buf.push(0x13),
+        Op::Over => buf.push(0x14),
+        Op::Pick(v) => {
+            buf.push(0x15);
+            buf.push(*v);
+        }
+        Op::Swap => buf.push(0x16),
+        Op::Rot => buf.push(0x17),
+        Op::Xderef => buf.push(0x18),
+        Op::Abs => buf.push(0x19),
+        Op::And => buf.push(0x1a),
+        Op::Div => buf.push(0x1b),
+        Op::Minus => buf.push(0x1c),
+        Op::Mod => buf.push(0x1d),
+        Op::Mul => buf.push(0x1e),
+        Op::Neg => buf.push(0x1f),
+        Op::Not => buf.push(0x20),
+        Op::Or => buf.push(0x21),
+        Op::Plus => buf.push(0x22),
+        Op::PlusConst(v) => {
+            buf.push(0x23);
+            push_uleb128(&mut buf, *v);
+        }
+        Op::Shl => buf.push(0x24),
+        Op::Shr => buf.push(0x25),
+        Op::Shra => buf.push(0x26),
+        Op::Xor => buf.push(0x27),
+        Op::Bra(off) => {
+            buf.push(0x28);
+            buf.extend_from_slice(&off.to_le_bytes());
+        }
+        Op::Eq => buf.push(0x29),
+        Op::Ge => buf.push(0x2a),
+        Op::Gt => buf.push(0x2b),
+        Op::Le => buf.push(0x2c),
+        Op::Lt => buf.push(0x2d),
+        Op::Ne => buf.push(0x2e),
+        Op::Skip(off) => {
+            buf.push(0x2f);
+            buf.extend_from_slice(&off.to_le_bytes());
+        }
+        Op::Lit(n) => {
+            check_base("lit", *n)?;
+            buf.push(0x30 + n);
+        }
+        Op::Reg(n) => {
+            check_base("reg", *n)?;
+            buf.push(0x50 + n);
+        }
+        Op::BReg(n, off) => {
+            check_base("breg", *n)?;
+            buf.push(0x70 + n);
+            push_sleb128(&mut buf, *off as i64);
+        }
+        Op::RegX(v) => {
+            buf.push(0x90);
+            push_uleb128(&mut buf, *v);
+        }
+        Op::FBReg(off) => {
+            buf.push(0x91);
+            push_sleb128(&mut buf, *off);
+        }
+        Op::BRegX(reg, off) => {
+            buf.push(0x92);
+            push_uleb128(&mut buf, *reg);
+            push_sleb128(&mut buf, *off as i64);
+        }
+        Op::Piece(sz) => {
+            buf.push(0x93);
+            push_uleb128(&mut buf, *sz);
+        }
+        Op::DerefSize(sz) => {
+            buf.push(0x94);
+            buf.push(*sz as u8);
+        }
+        Op::XDerefSize(sz) => {
+            buf.push(0x95);
+            buf.push(*sz as u8);
+        }
+        Op::Nop => buf.push(0x96),
+        Op::PushObjectAddress => buf.push(0x97),
+        Op::Call2(v) => {
+            buf.push(0x98);
+            buf.extend_from_slice(&v.to_le_bytes());
+        }
+        Op::Call4(v) => {
+            buf.push(0x99);
+            buf.extend_from_slice(&v.to_le_bytes());
+        }
+        Op::CallRef(off) => {
+            buf.push(0x9a);
+            buf.extend_from_slice(&(*off as u32).to_le_bytes());
+        }
+        Op::FormTlsAddress => buf.push(0x9b),
+        Op::CallFrameCfa => buf.push(0x9c),
+        Op::BitPiece(sz, off) => {
+            buf.push(0x9d);
+            push_uleb128(&mut buf, *sz);
+            push_uleb128(&mut buf, *off);
+        }
+        Op::ImplicitValue(bytes) => {
+            buf.push(0x9e);
+            push_uleb128(&mut buf, bytes.len() as u64);
+            buf.extend_from_slice(bytes);
+        }
+        Op::StackValue => buf.push(0x9f),
+        Op::ImplicitPointer(off, idx) => {
+            buf.push(0xa0);
+            buf.extend_from_slice(&(*off as u32).to_le_bytes());
+            push_sleb128(&mut buf, *idx);
+        }
+        Op::Addrx(idx) => {
+            buf.push(0xa1);
+            push_uleb128(&mut buf, *idx);
+        }
+        Op::Constx(idx) => {
+            buf.push(0xa2);
+            push_uleb128(&mut buf, *idx);
+        }
+        Op::EntryValue(bytes) => {
+            buf.push(0xa3);
+            push_uleb128(&mut buf, bytes.len() as u64);
+            buf.extend_from_slice(bytes);
+        }
+        Op::ConstType(type_off, bytes) => {
+            buf.push(0xa4);
+            push_uleb128(&mut buf, *type_off);
+            buf.push(bytes.len() as u8);
+            buf.extend_from_slice(bytes);
+        }
+        Op::RegvalType(reg, type_off) => {
+            buf.push(0xa5);
+            push_uleb128(&mut buf, *reg);
+            push_uleb128(&mut buf, *type_off);
+        }
+        Op::DerefType(sz, type_off) => {
+            buf.push(0xa6);
+            buf.push(*sz);
+            push_uleb128(&mut buf, *type_off);
+        }
+        Op::XDerefType(sz, type_off) => {
+            buf.push(0xa7);
+            buf.push(*sz);
+            push_uleb128(&mut buf, *type_off);
+        }
+        Op::Convert(type_off) => {
+            buf.push(0xa8);
+            push_uleb128(&mut buf, *type_off);
+        }
+        Op::Reinterpret(type_off) => {
+            buf.push(0xa9);
+            push_uleb128(&mut buf, *type_off);
+        }
+        Op::GnuImplicitPointer(off, idx) => {
+            buf.push(0xf2);
+            buf.extend_from_slice(&(*off as u32).to_le_bytes());
+            push_sleb128(&mut buf, *idx);
+        }
+        Op::GnuEntryValue(bytes) => {
+            buf.push(0xf3);
+            push_uleb128(&mut buf, bytes.len() as u64);
+            buf.extend_from_slice(bytes);
+        }
+        Op::GnuConstType(type_off, bytes) => {
+            buf.push(0xf4);
+            push_uleb128(&mut buf, *type_off);
+            buf.push(bytes.len() as u8);
+            buf.extend_from_slice(bytes);
+        }
+        Op::GnuRegvalType(reg, type_off) => {
+            buf.push(0xf5);
+            push_uleb128(&mut buf, *reg);
+            push_uleb128(&mut buf, *type_off);
+        }
+        Op::GnuDerefType(sz, type_off) => {
+            buf.push(0xf6);
+            buf.push(*sz);
+            push_uleb128(&mut buf, *type_off);
+        }
+        Op::GnuConvert(type_off) => {
+            buf.push(0xf7);
+            push_uleb128(&mut buf, *type_off);
+        }
+        Op::GnuReinterpret(type_off) => {
+            buf.push(0xf9);
+            push_uleb128(&mut buf, *type_off);
+        }
+        Op::GnuParameterRef(off) => {
+            buf.push(0xfa);
+            buf.extend_from_slice(&off.to_le_bytes());
+        }
+        Op::GnuUnknown(op) => buf.push(*op),
+    }
+
+    Ok(buf)
+}
+
+/// Encodes a whole expression, in order.
+pub fn assemble(ops: &[Op]) -> Result<Vec<u8>, DwarfDisError> {
+    let mut buf = Vec::new();
+    for op in ops {
+        buf.extend(encode(op)?);
+    }
+
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode;
+
+    /// Every `Op` variant generated from `opcodes.in`, one representative
+    /// value each, asserts `decode(encode(op)) == op`. `encode` hand-
+    /// transcribes each opcode byte independently of the generated decoder,
+    /// so every variant needs its own case here to catch a transposed byte.
+    #[test]
+    fn round_trips_through_decode() {
+        let ops = vec![
+            Op::Addr(0x1122334455667788),
+            Op::Deref,
+            Op::Const1u(0x12),
+            Op::Const1s(-1),
+            Op::Const2u(0x1234),
+            Op::Const2s(-1),
+            Op::Const4u(0x12345678),
+            Op::Const4s(-1),
+            Op::Const8u(0x1122334455667788),
+            Op::Const8s(-1),
+            Op::Constu(u64::MAX),
+            Op::Consts(i64::MIN),
+            Op::Dup,
+            Op::Drop,
+            Op::Over,
+            Op::Pick(3),
+            Op::Swap,
+            Op::Rot,
+            Op::Xderef,
+            Op::Abs,
+            Op::And,
+            Op::Div,
+            Op::Minus,
+            Op::Mod,
+            Op::Mul,
+            Op::Neg,
+            Op::Not,
+            Op::Or,
+            Op::Plus,
+            Op::PlusConst(9),
+            Op::Shl,
+            Op::Shr,
+            Op::Shra,
+            Op::Xor,
+            Op::Bra(-4),
+            Op::Eq,
+            Op::Ge,
+            Op::Gt,
+            Op::Le,
+            Op::Lt,
+            Op::Ne,
+            Op::Skip(4),
+            Op::Lit(7),
+            Op::Reg(7),
+            Op::BReg(7, -8),
+            Op::RegX(300),
+            Op::FBReg(-16),
+            Op::BRegX(300, -16),
+            Op::Piece(4),
+            Op::DerefSize(4),
+            Op::XDerefSize(8),
+            Op::Nop,
+            Op::PushObjectAddress,
+            Op::Call2(0x1234),
+            Op::Call4(0x12345678),
+            Op::CallRef(0xdeadbeef),
+            Op::FormTlsAddress,
+            Op::CallFrameCfa,
+            Op::BitPiece(4, 8),
+            Op::ImplicitValue(vec![1, 2, 3, 4]),
+            Op::StackValue,
+            Op::ImplicitPointer(0xdeadbeef, -1),
+            Op::Addrx(42),
+            Op::Constx(43),
+            Op::EntryValue(vec![0x30]),
+            Op::ConstType(5, vec![1, 2, 3, 4]),
+            Op::RegvalType(6, 7),
+            Op::DerefType(8, 9),
+            Op::XDerefType(8, 9),
+            Op::Convert(10),
+            Op::Reinterpret(11),
+            Op::GnuUnknown(0xe0),
+            Op::GnuImplicitPointer(0xdeadbeef, -1),
+            Op::GnuEntryValue(vec![0x30]),
+            Op::GnuConstType(5, vec![1, 2, 3, 4]),
+            Op::GnuRegvalType(6, 7),
+            Op::GnuDerefType(8, 9),
+            Op::GnuConvert(10),
+            Op::GnuReinterpret(11),
+            Op::GnuParameterRef(0xdeadbeef),
+        ];
+
+        for op in ops {
+            let bytes = encode(&op).unwrap();
+            let (sz, decoded) = decode(&bytes).unwrap();
+            assert_eq!(sz, bytes.len());
+            assert_eq!(decoded, op);
+        }
+    }
+
+    #[test]
+    fn rejects_out_of_range_base_values() {
+        let cases = [
+            (Op::Lit(32), "lit"),
+            (Op::Reg(32), "reg"),
+            (Op::BReg(32, 0), "breg"),
+        ];
+
+        for (op, mnem) in cases {
+            assert_eq!(
+                encode(&op).unwrap_err(),
+                DwarfDisError::BaseOverflow { mnem, value: 32 }
+            );
+        }
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+
+use crate::{DwarfDisError, Instructions, Op};
+
+/// Host callbacks needed to evaluate the ops that reach outside the stack
+/// machine itself (`DW_OP_deref*`, `DW_OP_reg*`, `DW_OP_breg*`, ...).
+pub trait EvalContext {
+    fn read_memory(&mut self, addr: u64, size: u8) -> Result<u64, DwarfDisError>;
+    fn read_register(&mut self, reg: u64) -> Result<u64, DwarfDisError>;
+}
+
+/// Executes a DWARF expression as a stack machine over `u64` values.
+///
+/// This is the two-phase design gimli's `op` module uses: `decode` turns
+/// bytecode into `Op`s, `Evaluator` runs them. Branch targets are byte
+/// offsets into the original bytecode, so the evaluator decodes the whole
+/// expression up front and keeps a map from byte offset to op index.
+pub struct Evaluator<'a> {
+    bytecode: &'a [u8],
+    stack: Vec<u64>,
+}
+
+impl<'a> Evaluator<'a> {
+    pub fn new(bytecode: &'a [u8]) -> Self {
+        Evaluator {
+            bytecode,
+            stack: Vec::new(),
+        }
+    }
+
+    fn pop(&mut self) -> Result<u64, DwarfDisError> {
+        self.stack.pop().ok_or(DwarfDisError::StackUnderflow)
+    }
+
+    fn peek(&self, depth: usize) -> Result<u64, DwarfDisError> {
+        let len = self.stack.len();
+        if depth >= len {
+            return Err(DwarfDisError::StackUnderflow);
+        }
+
+        Ok(self.stack[len - 1 - depth])
+    }
+
+    /// Runs the whole expression and returns the value left on top of the
+    /// stack.
+    pub fn evaluate(&mut self, ctx: &mut dyn EvalContext) -> Result<u64, DwarfDisError> {
+        let ops: Vec<(usize, usize, Op)> =
+            Instructions::new(self.bytecode).collect::<Result<_, _>>()?;
+
+        let pc_to_idx: HashMap<usize, usize> = ops
+            .iter()
+            .enumerate()
+            .map(|(idx, (pc, _, _))| (*pc, idx))
+            .collect();
+
+        let mut ip = 0;
+        while ip < ops.len() {
+            let (pc, sz, ref op) = ops[ip];
+
+            let mut branch = None;
+            self.step(op, ctx, &mut branch)?;
+
+            if let Some(off) = branch {
+                let target = (pc as isize + sz as isize + off as isize) as usize;
+
+                // A branch to one past the last op is how a DWARF expression
+                // signals "terminate evaluation here" (see gimli's `op`
+                // module), not an error.
+                if target == self.bytecode.len() {
+                    break;
+                }
+
+                ip = *pc_to_idx
+                    .get(&target)
+                    .ok_or(DwarfDisError::InvalidBranchTarget(target))?;
+                continue;
+            }
+
+            ip += 1;
+        }
+
+        self.pop()
+    }
+
+    fn step(
+        &mut self,
+        op: &Op,
+        ctx: &mut dyn EvalContext,
+        branch: &mut Option<i16>,
+    ) -> Result<(), DwarfDisError> {
+        match *op {
+            Op::Addr(addr) => self.stack.push(addr),
+            Op::Deref => {
+                let addr = self.pop()?;
+                self.stack.push(ctx.read_memory(addr, 8)?);
+            }
+            Op::DerefSize(sz) => {
+                let addr = self.pop()?;
+                self.stack.push(ctx.read_memory(addr, sz as u8)?);
+            }
+            Op::Const1u(v) => self.stack.push(v as u64),
+            Op::Const1s(v) => self.stack.push(v as i64 as u64),
+            Op::Const2u(v) => self.stack.push(v as u64),
+            Op::Const2s(v) => self.stack.push(v as i64 as u64),
+            Op::Const4u(v) => self.stack.push(v as u64),
+            Op::Const4s(v) => self.stack.push(v as i64 as u64),
+            Op::Const8u(v) => self.stack.push(v),
+            Op::Const8s(v) => self.stack.push(v as u64),
+            Op::Constu(v) => self.stack.push(v),
+            Op::Consts(v) => self.stack.push(v as u64),
+            Op::Dup => {
+                let top = self.peek(0)?;
+                self.stack.push(top);
+            }
+            Op::Drop => {
+                self.pop()?;
+            }
+            Op::Over => {
+                let v = self.peek(1)?;
+                self.stack.push(v);
+            }
+            Op::Pick(n) => {
+                let v = self.peek(n as usize)?;
+                self.stack.push(v);
+            }
+            Op::Swap => {
+                let a = self.pop()?;
+                let b = self.pop()?;
+                self.stack.push(a);
+                self.stack.push(b);
+            }
+            Op::Rot => {
+                let a = self.pop()?;
+                let b = self.pop()?;
+                let c = self.pop()?;
+                self.stack.push(a);
+                self.stack.push(c);
+                self.stack.push(b);
+            }
+            Op::Abs => {
+                let v = self.pop()? as i64;
+                self.stack.push(v.wrapping_abs() as u64);
+            }
+            Op::And => {
+                let rhs = self.pop()?;
+                let lhs = self.pop()?;
+                self.stack.push(lhs & rhs);
+            }
+            Op::Div => {
+                let rhs = self.pop()? as i64;
+                let lhs = self.pop()? as i64;
+                if rhs == 0 {
+                    return Err(DwarfDisError::DivideByZero);
+                }
+                self.stack.push(lhs.wrapping_div(rhs) as u64);
+            }
+            Op::Minus => {
+                let rhs = self.pop()?;
+                let lhs = self.pop()?;
+                self.stack.push(lhs.wrapping_sub(rhs));
+            }
+            Op::Mod => {
+                let rhs = self.pop()?;
+                let lhs = self.pop()?;
+                if rhs == 0 {
+                    return Err(DwarfDisError::DivideByZero);
+                }
+                self.stack.push(lhs.wrapping_rem(rhs));
+            }
+            Op::Mul => {
+                let rhs = self.pop()?;
+                let lhs = self.pop()?;
+                self.stack.push(lhs.wrapping_mul(rhs));
+            }
+            Op::Neg => {
+                let v = self.pop()? as i64;
+                self.stack.push(v.wrapping_neg() as u64);
+            }
+            Op::Not => {
+                let v = self.pop()?;
+                self.stack.push(!v);
+            }
+            Op::Or => {
+                let rhs = self.pop()?;
+                let lhs = self.pop()?;
+                self.stack.push(lhs | rhs);
+            }
+            Op::Plus => {
+                let rhs = self.pop()?;
+                let lhs = self.pop()?;
+                self.stack.push(lhs.wrapping_add(rhs));
+            }
+            Op::PlusConst(c) => {
+                let v = self.pop()?;
+                self.stack.push(v.wrapping_add(c));
+            }
+            Op::Bra(off) => {
+                let v = self.pop()?;
+                if v != 0 {
+                    *branch = Some(off);
+                }
+            }
+            Op::Eq => self.cmp(|a, b| a == b)?,
+            Op::Ge => self.cmp(|a, b| a >= b)?,
+            Op::Gt => self.cmp(|a, b| a > b)?,
+            Op::Le => self.cmp(|a, b| a <= b)?,
+            Op::Lt => self.cmp(|a, b| a < b)?,
+            Op::Ne => self.cmp(|a, b| a != b)?,
+            Op::Shl => {
+                let rhs = self.pop()?;
+                let lhs = self.pop()?;
+                self.stack.push(lhs.wrapping_shl(rhs as u32));
+            }
+            Op::Shr => {
+                let rhs = self.pop()?;
+                let lhs = self.pop()?;
+                self.stack.push(lhs.wrapping_shr(rhs as u32));
+            }
+            Op::Shra => {
+                let rhs = self.pop()?;
+                let lhs = self.pop()? as i64;
+                self.stack.push(lhs.wrapping_shr(rhs as u32) as u64);
+            }
+            Op::Xor => {
+                let rhs = self.pop()?;
+                let lhs = self.pop()?;
+                self.stack.push(lhs ^ rhs);
+            }
+            Op::Skip(off) => *branch = Some(off),
+            Op::Lit(n) => self.stack.push(n as u64),
+            Op::Reg(n) => self.stack.push(ctx.read_register(n as u64)?),
+            Op::BReg(n, off) => {
+                let v = ctx.read_register(n as u64)? as i64;
+                self.stack.push(v.wrapping_add(off as i64) as u64);
+            }
+            Op::RegX(n) => self.stack.push(ctx.read_register(n)?),
+            Op::BRegX(n, off) => {
+                let v = ctx.read_register(n)? as i64;
+                self.stack.push(v.wrapping_add(off as i64) as u64);
+            }
+            Op::Nop => {}
+            ref other => return Err(DwarfDisError::Unsupported(other.clone())),
+        }
+
+        Ok(())
+    }
+
+    fn cmp(&mut self, f: impl Fn(i64, i64) -> bool) -> Result<(), DwarfDisError> {
+        let rhs = self.pop()? as i64;
+        let lhs = self.pop()? as i64;
+        self.stack.push(f(lhs, rhs) as u64);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assemble, encode, Op};
+
+    /// An `EvalContext` for expressions that never touch memory/registers.
+    struct NoopContext;
+
+    impl EvalContext for NoopContext {
+        fn read_memory(&mut self, _addr: u64, _size: u8) -> Result<u64, DwarfDisError> {
+            unreachable!("test expression does not read memory")
+        }
+
+        fn read_register(&mut self, _reg: u64) -> Result<u64, DwarfDisError> {
+            unreachable!("test expression does not read registers")
+        }
+    }
+
+    #[test]
+    fn evaluates_arithmetic() {
+        let bytecode = assemble(&[Op::Lit(5), Op::Lit(3), Op::Plus]).unwrap();
+        let result = Evaluator::new(&bytecode).evaluate(&mut NoopContext).unwrap();
+        assert_eq!(result, 8);
+    }
+
+    #[test]
+    fn evaluates_conditional_branch() {
+        // lit7 (kept); lit1 (cond); bra over the decoy op -- the branch is
+        // taken (cond != 0), so the final value is the 7 left on the stack
+        // by the first `lit`, not the decoy's 9.
+        let decoy = encode(&Op::Lit(9)).unwrap();
+        let mut bytecode =
+            assemble(&[Op::Lit(7), Op::Lit(1), Op::Bra(decoy.len() as i16)]).unwrap();
+        bytecode.extend(decoy);
+
+        let result = Evaluator::new(&bytecode).evaluate(&mut NoopContext).unwrap();
+        assert_eq!(result, 7);
+    }
+
+    #[test]
+    fn division_by_zero_is_an_error() {
+        let bytecode = assemble(&[Op::Lit(5), Op::Lit(0), Op::Div]).unwrap();
+        let err = Evaluator::new(&bytecode).evaluate(&mut NoopContext).unwrap_err();
+        assert_eq!(err, DwarfDisError::DivideByZero);
+    }
+
+    #[test]
+    fn remainder_by_zero_is_an_error() {
+        let bytecode = assemble(&[Op::Lit(5), Op::Lit(0), Op::Mod]).unwrap();
+        let err = Evaluator::new(&bytecode).evaluate(&mut NoopContext).unwrap_err();
+        assert_eq!(err, DwarfDisError::DivideByZero);
+    }
+
+    #[test]
+    fn branch_to_end_of_bytecode_terminates_evaluation() {
+        let bytecode = assemble(&[Op::Lit(5), Op::Skip(0)]).unwrap();
+        let result = Evaluator::new(&bytecode).evaluate(&mut NoopContext).unwrap();
+        assert_eq!(result, 5);
+    }
+}
@@ -0,0 +1,46 @@
+use crate::{decode, DwarfDisError, Op};
+
+/// Iterates a whole expression, decoding one `Op` per step, so callers don't
+/// have to reimplement the pc/size bookkeeping `decode` otherwise leaves to
+/// them.
+pub struct Instructions<'a> {
+    bytecode: &'a [u8],
+    pc: usize,
+    done: bool,
+}
+
+impl<'a> Instructions<'a> {
+    pub fn new(bytecode: &'a [u8]) -> Self {
+        Instructions {
+            bytecode,
+            pc: 0,
+            done: false,
+        }
+    }
+}
+
+impl<'a> Iterator for Instructions<'a> {
+    type Item = Result<(usize, usize, Op), DwarfDisError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || self.pc >= self.bytecode.len() {
+            return None;
+        }
+
+        match decode(&self.bytecode[self.pc..]) {
+            Ok((sz, op)) => {
+                let pc = self.pc;
+                self.pc += sz;
+
+                Some(Ok((pc, sz, op)))
+            }
+            Err(e) => {
+                // A decode error means the rest of the buffer can't be
+                // trusted either, so stop here instead of looping forever.
+                self.done = true;
+
+                Some(Err(e))
+            }
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::convert::TryInto;
+
+use nano_leb128::{LEB128DecodeError, SLEB128, ULEB128};
+
+use crate::DwarfDisError;
+
+/// A cursor over `&[u8]` that turns short reads into `Err` instead of
+/// panicking, so `decode` can be handed truncated or attacker-controlled
+/// bytecode (e.g. from a fuzz target) without aborting the process.
+pub(crate) struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Reader { data, pos: 0 }
+    }
+
+    pub(crate) fn pos(&self) -> usize {
+        self.pos
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    fn need(&self, n: usize) -> Result<(), DwarfDisError> {
+        if self.remaining() < n {
+            return Err(DwarfDisError::UnexpectedEof {
+                needed: n,
+                remaining: self.remaining(),
+            });
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn read_bytes(&mut self, n: usize) -> Result<&'a [u8], DwarfDisError> {
+        self.need(n)?;
+
+        let bytes = &self.data[self.pos..self.pos + n];
+        self.pos += n;
+
+        Ok(bytes)
+    }
+
+    pub(crate) fn read_u8(&mut self) -> Result<u8, DwarfDisError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    pub(crate) fn read_i8(&mut self) -> Result<i8, DwarfDisError> {
+        Ok(self.read_bytes(1)?[0] as i8)
+    }
+
+    pub(crate) fn read_u16(&mut self) -> Result<u16, DwarfDisError> {
+        let bytes = self.read_bytes(2)?;
+        Ok(u16::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    pub(crate) fn read_i16(&mut self) -> Result<i16, DwarfDisError> {
+        let bytes = self.read_bytes(2)?;
+        Ok(i16::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    pub(crate) fn read_u32(&mut self) -> Result<u32, DwarfDisError> {
+        let bytes = self.read_bytes(4)?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    pub(crate) fn read_i32(&mut self) -> Result<i32, DwarfDisError> {
+        let bytes = self.read_bytes(4)?;
+        Ok(i32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    pub(crate) fn read_u64(&mut self) -> Result<u64, DwarfDisError> {
+        let bytes = self.read_bytes(8)?;
+        Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    pub(crate) fn read_i64(&mut self) -> Result<i64, DwarfDisError> {
+        let bytes = self.read_bytes(8)?;
+        Ok(i64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    pub(crate) fn read_uleb128(&mut self) -> Result<u64, DwarfDisError> {
+        match ULEB128::read_from(&self.data[self.pos..]) {
+            Ok((val, sz)) => {
+                self.pos += sz;
+                Ok(val.into())
+            }
+            Err(LEB128DecodeError::IntegerOverflow) => Err(DwarfDisError::LebOverflow),
+            Err(LEB128DecodeError::BufferOverflow) => Err(DwarfDisError::UnexpectedEof {
+                needed: self.remaining() + 1,
+                remaining: self.remaining(),
+            }),
+        }
+    }
+
+    pub(crate) fn read_sleb128(&mut self) -> Result<i64, DwarfDisError> {
+        match SLEB128::read_from(&self.data[self.pos..]) {
+            Ok((val, sz)) => {
+                self.pos += sz;
+                Ok(val.into())
+            }
+            Err(LEB128DecodeError::IntegerOverflow) => Err(DwarfDisError::LebOverflow),
+            Err(LEB128DecodeError::BufferOverflow) => Err(DwarfDisError::UnexpectedEof {
+                needed: self.remaining() + 1,
+                remaining: self.remaining(),
+            }),
+        }
+    }
+}